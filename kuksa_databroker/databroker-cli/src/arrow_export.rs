@@ -0,0 +1,377 @@
+/********************************************************************************
+* Copyright (c) 2023 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Exports signal samples captured by `record` as Apache Arrow columnar files
+//! (IPC/Feather or Parquet), so large recorded traces can be loaded directly
+//! into pandas/polars/DuckDB instead of parsed line-by-line from JSON.
+//!
+//! A [`Sample`] is the same (path, timestamp, value) triple `record` already
+//! writes as JSON lines. [`build_record_batch`] pivots a window of them into
+//! one row per distinct timestamp — all the samples a single subscription
+//! message produced share their `ts`, so grouping by it reassembles the
+//! message — plus one column per distinct signal path, with each column's
+//! Arrow type chosen from the VSS [`DataType`](proto::DataType) of that
+//! path's values. A row with no sample for a given path in that group, or a
+//! `FailureValue`/`NotAvailable` sample, is stored as an Arrow null rather
+//! than a sentinel value.
+//!
+//! Array-typed signals (`Int32Array`, `StringArray`, ...) are stored as their
+//! JSON encoding in a Utf8 column rather than a native Arrow list column —
+//! the formats this module targets are column-at-a-time, whereas array
+//! datapoints are a minority of signals and a nested list builder per
+//! possible element type isn't worth the complexity it'd add here.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BooleanBuilder, Float32Builder, Float64Builder, Int32Builder, Int64Builder,
+    StringBuilder, UInt32Builder, UInt64Builder,
+};
+use arrow::datatypes::{DataType as ArrowDataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+
+use databroker_proto::sdv::databroker::v1 as proto;
+
+/// A single recorded datapoint: the signal path it belongs to, its timestamp
+/// in nanoseconds since the Unix epoch, and its value (`None` for
+/// `FailureValue`/`NotAvailable`, which become Arrow nulls).
+pub struct Sample {
+    pub path: String,
+    pub ts: u128,
+    pub value: Option<proto::datapoint::Value>,
+}
+
+/// The columnar container format to export to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Arrow,
+    Parquet,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "arrow" | "ipc" | "feather" => Ok(ExportFormat::Arrow),
+            "parquet" => Ok(ExportFormat::Parquet),
+            other => Err(format!(
+                "unknown export format \"{other}\" (expected one of: arrow, parquet)"
+            )),
+        }
+    }
+}
+
+/// The VSS [`proto::DataType`] a value variant was produced from, used only
+/// to pick the Arrow column type for a path — see [`data_type_to_arrow`].
+fn value_data_type(value: &proto::datapoint::Value) -> proto::DataType {
+    use proto::datapoint::Value::*;
+    match value {
+        BoolValue(_) | BoolArray(_) => proto::DataType::Bool,
+        Int32Value(_) | Int32Array(_) => proto::DataType::Int32,
+        Int64Value(_) | Int64Array(_) => proto::DataType::Int64,
+        Uint32Value(_) | Uint32Array(_) => proto::DataType::Uint32,
+        Uint64Value(_) | Uint64Array(_) => proto::DataType::Uint64,
+        FloatValue(_) | FloatArray(_) => proto::DataType::Float,
+        DoubleValue(_) | DoubleArray(_) => proto::DataType::Double,
+        StringValue(_) | StringArray(_) | FailureValue(_) => proto::DataType::String,
+    }
+}
+
+/// Maps a VSS [`proto::DataType`] to the Arrow type used to store it. Array
+/// variants map to Utf8, since arrays are stored pre-encoded as JSON text
+/// (see the module docs).
+fn data_type_to_arrow(data_type: proto::DataType) -> ArrowDataType {
+    use proto::DataType::*;
+    match data_type {
+        Bool => ArrowDataType::Boolean,
+        Int8 | Int16 | Int32 => ArrowDataType::Int32,
+        Int64 => ArrowDataType::Int64,
+        Uint8 | Uint16 | Uint32 => ArrowDataType::UInt32,
+        Uint64 => ArrowDataType::UInt64,
+        Float => ArrowDataType::Float32,
+        Double => ArrowDataType::Float64,
+        // String, and every array variant.
+        _ => ArrowDataType::Utf8,
+    }
+}
+
+/// Renders a value as the Utf8 fallback used for array variants.
+fn value_to_json_string(value: &proto::datapoint::Value) -> String {
+    use proto::datapoint::Value::*;
+    match value {
+        StringArray(array) => serde_json::json!(array.values).to_string(),
+        BoolArray(array) => serde_json::json!(array.values).to_string(),
+        Int32Array(array) => serde_json::json!(array.values).to_string(),
+        Int64Array(array) => serde_json::json!(array.values).to_string(),
+        Uint32Array(array) => serde_json::json!(array.values).to_string(),
+        Uint64Array(array) => serde_json::json!(array.values).to_string(),
+        FloatArray(array) => serde_json::json!(array.values).to_string(),
+        DoubleArray(array) => serde_json::json!(array.values).to_string(),
+        BoolValue(value) => value.to_string(),
+        Int32Value(value) => value.to_string(),
+        Int64Value(value) => value.to_string(),
+        Uint32Value(value) => value.to_string(),
+        Uint64Value(value) => value.to_string(),
+        FloatValue(value) => value.to_string(),
+        DoubleValue(value) => value.to_string(),
+        StringValue(value) => value.clone(),
+        FailureValue(_) => String::new(),
+    }
+}
+
+/// One output row: a shared timestamp plus each distinct path's value for
+/// that timestamp (`None` where that path had no sample in this row).
+type Row<'a> = (u128, Vec<Option<&'a proto::datapoint::Value>>);
+
+/// Groups `samples` into rows by timestamp — all the samples one
+/// subscription message produced share their `ts` (see the module docs), so
+/// this reassembles one row per message — preserving first-seen order of
+/// both rows and paths.
+fn group_into_rows(samples: &[Sample]) -> (Vec<&str>, Vec<Row<'_>>) {
+    let mut paths = Vec::new();
+    let mut path_index = HashMap::new();
+    for sample in samples {
+        path_index
+            .entry(sample.path.as_str())
+            .or_insert_with(|| {
+                paths.push(sample.path.as_str());
+                paths.len() - 1
+            });
+    }
+
+    let mut rows: Vec<Row> = Vec::new();
+    let mut row_index = HashMap::new();
+    for sample in samples {
+        let row_idx = *row_index.entry(sample.ts).or_insert_with(|| {
+            rows.push((sample.ts, vec![None; paths.len()]));
+            rows.len() - 1
+        });
+        rows[row_idx].1[path_index[sample.path.as_str()]] = sample.value.as_ref();
+    }
+
+    (paths, rows)
+}
+
+/// Builds one signal path's column: `None` for rows with no sample for this
+/// path, or whose sample is a `FailureValue`/`NotAvailable`; the value
+/// otherwise, coerced into `arrow_type`.
+fn build_column(arrow_type: ArrowDataType, rows: &[Row], path_idx: usize) -> Result<ArrayRef, String> {
+    let values: Vec<Option<&proto::datapoint::Value>> =
+        rows.iter().map(|(_, columns)| columns[path_idx]).collect();
+
+    macro_rules! build_numeric {
+        ($builder:ty, $extract:expr) => {{
+            let mut builder = <$builder>::with_capacity(values.len());
+            for value in &values {
+                match value {
+                    Some(proto::datapoint::Value::FailureValue(_)) | None => builder.append_null(),
+                    Some(value) => match $extract(value) {
+                        Some(extracted) => builder.append_value(extracted),
+                        None => builder.append_null(),
+                    },
+                }
+            }
+            Ok(Arc::new(builder.finish()) as ArrayRef)
+        }};
+    }
+
+    match arrow_type {
+        ArrowDataType::Boolean => build_numeric!(BooleanBuilder, |value| match value {
+            proto::datapoint::Value::BoolValue(value) => Some(*value),
+            _ => None,
+        }),
+        ArrowDataType::Int32 => build_numeric!(Int32Builder, |value| match value {
+            proto::datapoint::Value::Int32Value(value) => Some(*value),
+            _ => None,
+        }),
+        ArrowDataType::Int64 => build_numeric!(Int64Builder, |value| match value {
+            proto::datapoint::Value::Int64Value(value) => Some(*value),
+            _ => None,
+        }),
+        ArrowDataType::UInt32 => build_numeric!(UInt32Builder, |value| match value {
+            proto::datapoint::Value::Uint32Value(value) => Some(*value),
+            _ => None,
+        }),
+        ArrowDataType::UInt64 => build_numeric!(UInt64Builder, |value| match value {
+            proto::datapoint::Value::Uint64Value(value) => Some(*value),
+            _ => None,
+        }),
+        ArrowDataType::Float32 => build_numeric!(Float32Builder, |value| match value {
+            proto::datapoint::Value::FloatValue(value) => Some(*value),
+            _ => None,
+        }),
+        ArrowDataType::Float64 => build_numeric!(Float64Builder, |value| match value {
+            proto::datapoint::Value::DoubleValue(value) => Some(*value),
+            _ => None,
+        }),
+        _ => {
+            let mut builder = StringBuilder::with_capacity(values.len(), values.len() * 8);
+            for value in &values {
+                match value {
+                    Some(proto::datapoint::Value::FailureValue(_)) | None => builder.append_null(),
+                    Some(proto::datapoint::Value::StringValue(value)) => {
+                        builder.append_value(value)
+                    }
+                    Some(value) => builder.append_value(value_to_json_string(value)),
+                }
+            }
+            Ok(Arc::new(builder.finish()) as ArrayRef)
+        }
+    }
+}
+
+/// Builds one [`RecordBatch`] from a window of samples: a shared `ts` column
+/// (nanoseconds since the Unix epoch) plus one column per distinct signal
+/// path, in first-seen order, with one row per distinct timestamp (see
+/// [`group_into_rows`]).
+pub fn build_record_batch(samples: &[Sample]) -> Result<RecordBatch, String> {
+    let (paths, rows) = group_into_rows(samples);
+
+    let mut fields = vec![Field::new("ts", ArrowDataType::Int64, false)];
+    let mut arrays: Vec<ArrayRef> = Vec::with_capacity(paths.len() + 1);
+
+    let mut ts_builder = Int64Builder::with_capacity(rows.len());
+    for (ts, _) in &rows {
+        ts_builder.append_value(*ts as i64);
+    }
+    arrays.push(Arc::new(ts_builder.finish()));
+
+    for (path_idx, path) in paths.iter().enumerate() {
+        let data_type = samples
+            .iter()
+            .filter(|sample| sample.path == *path)
+            .filter_map(|sample| sample.value.as_ref())
+            .find(|value| !matches!(value, proto::datapoint::Value::FailureValue(_)))
+            .map(value_data_type)
+            .unwrap_or(proto::DataType::String);
+        let arrow_type = data_type_to_arrow(data_type);
+        fields.push(Field::new(*path, arrow_type.clone(), true));
+        arrays.push(build_column(arrow_type, &rows, path_idx)?);
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays).map_err(|err| err.to_string())
+}
+
+/// Writes `batch` to `path` as an Arrow IPC (Feather) file.
+pub fn write_ipc(batch: &RecordBatch, path: &std::path::Path) -> Result<(), String> {
+    let file = std::fs::File::create(path)
+        .map_err(|err| format!("Failed to create \"{}\": {err}", path.display()))?;
+    let mut writer = arrow::ipc::writer::FileWriter::try_new(file, batch.schema().as_ref())
+        .map_err(|err| err.to_string())?;
+    writer.write(batch).map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())
+}
+
+/// Writes `batch` to `path` as a Parquet file.
+pub fn write_parquet(batch: &RecordBatch, path: &std::path::Path) -> Result<(), String> {
+    let file = std::fs::File::create(path)
+        .map_err(|err| format!("Failed to create \"{}\": {err}", path.display()))?;
+    let mut writer = parquet::arrow::ArrowWriter::try_new(file, batch.schema(), None)
+        .map_err(|err| err.to_string())?;
+    writer.write(batch).map_err(|err| err.to_string())?;
+    writer.close().map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arrow::array::{Array, Int32Array, Int64Array, StringArray};
+
+    #[test]
+    fn pivots_two_paths_into_one_row_per_timestamp() {
+        let samples = vec![
+            Sample {
+                path: "Vehicle.Speed".to_owned(),
+                ts: 100,
+                value: Some(proto::datapoint::Value::Int32Value(10)),
+            },
+            Sample {
+                path: "Vehicle.IsMoving".to_owned(),
+                ts: 100,
+                value: Some(proto::datapoint::Value::BoolValue(true)),
+            },
+            Sample {
+                path: "Vehicle.Speed".to_owned(),
+                ts: 200,
+                value: Some(proto::datapoint::Value::Int32Value(20)),
+            },
+        ];
+
+        let batch = build_record_batch(&samples).unwrap();
+
+        // One row per distinct timestamp, not one row per sample.
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.num_columns(), 3); // ts, Vehicle.Speed, Vehicle.IsMoving
+
+        let ts = batch
+            .column_by_name("ts")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(ts.value(0), 100);
+        assert_eq!(ts.value(1), 200);
+
+        let speed = batch
+            .column_by_name("Vehicle.Speed")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        assert_eq!(speed.value(0), 10);
+        assert_eq!(speed.value(1), 20);
+
+        // Vehicle.IsMoving has no sample in the second row: null, not 0/false.
+        let is_moving = batch.column_by_name("Vehicle.IsMoving").unwrap();
+        assert!(!is_moving.is_null(0));
+        assert!(is_moving.is_null(1));
+    }
+
+    #[test]
+    fn failure_value_becomes_null() {
+        let samples = vec![Sample {
+            path: "Vehicle.Speed".to_owned(),
+            ts: 100,
+            value: Some(proto::datapoint::Value::FailureValue(
+                proto::datapoint::Failure::NotAvailable as i32,
+            )),
+        }];
+
+        let batch = build_record_batch(&samples).unwrap();
+        let speed = batch.column_by_name("Vehicle.Speed").unwrap();
+        assert!(speed.is_null(0));
+    }
+
+    #[test]
+    fn array_valued_signal_is_stored_as_json_text() {
+        let samples = vec![Sample {
+            path: "Vehicle.Flags".to_owned(),
+            ts: 100,
+            value: Some(proto::datapoint::Value::Int32Array(proto::Int32Array {
+                values: vec![1, 2, 3],
+            })),
+        }];
+
+        let batch = build_record_batch(&samples).unwrap();
+        let flags = batch
+            .column_by_name("Vehicle.Flags")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(flags.value(0), "[1,2,3]");
+    }
+}
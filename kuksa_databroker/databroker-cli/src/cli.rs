@@ -0,0 +1,204 @@
+/********************************************************************************
+* Copyright (c) 2023 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Command-line argument parsing and small REPL-facing helpers (prompt text,
+//! response printing, line splitting) shared by `sdv_cli`. Kept separate from
+//! `sdv_cli` so the command loop only ever talks to [`Cli`]'s getters, never
+//! to `clap`'s attributes directly.
+
+use std::sync::Arc;
+
+use ansi_term::Color;
+use clap::{Parser, Subcommand};
+use linefeed::{Interface, Terminal};
+
+/// `sdv-cli` command-line arguments. Anything not covering a one-shot
+/// [`Commands`] drops into the interactive REPL (or `--script`/stdin batch
+/// mode, if given).
+#[derive(Debug, Parser)]
+#[command(name = "sdv-cli", about = "Command-line interface to a vehicle signal broker")]
+pub struct Cli {
+    /// Address of the broker to connect to.
+    #[arg(long, default_value = "http://127.0.0.1:55555")]
+    server: String,
+
+    /// Read an access token from FILE and use it for the initial connection.
+    #[arg(long)]
+    token_file: Option<String>,
+
+    /// Trust FILE (a PEM-encoded CA certificate) for TLS connections.
+    #[cfg(feature = "tls")]
+    #[arg(long)]
+    ca_cert: Option<String>,
+
+    /// Read commands from FILE (or stdin, with `-`) instead of the
+    /// interactive prompt, one per line.
+    #[arg(long)]
+    script: Option<String>,
+
+    /// Which gRPC API to start with: `sdv`, `kuksa.val.v1`, or `kuksa.val.v2`.
+    #[arg(long)]
+    protocol: Option<String>,
+
+    /// Output format for `get`/`metadata`/`subscribe`: `pretty` or `json`.
+    #[arg(long, short = 'o')]
+    output: Option<String>,
+
+    /// Load connection profiles from FILE, so `connect PROFILE` can use them.
+    #[arg(long = "config")]
+    config_file: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+/// A one-shot command, run non-interactively and exiting immediately
+/// instead of entering the REPL.
+#[derive(Debug, Clone, Subcommand)]
+pub enum Commands {
+    /// Get signal value(s) and exit.
+    Get {
+        /// Signal paths to fetch.
+        paths: Vec<String>,
+    },
+}
+
+impl Cli {
+    pub fn get_server(&self) -> &str {
+        &self.server
+    }
+
+    pub fn get_token_file(&self) -> Option<&str> {
+        self.token_file.as_deref()
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn get_ca_cert(&self) -> Option<&str> {
+        self.ca_cert.as_deref()
+    }
+
+    pub fn get_script(&self) -> Option<&str> {
+        self.script.as_deref()
+    }
+
+    pub fn get_protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    pub fn get_output_format(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+
+    pub fn get_config_file(&self) -> Option<&str> {
+        self.config_file.as_deref()
+    }
+
+    pub fn get_command(&self) -> Option<Commands> {
+        self.command.clone()
+    }
+}
+
+/// Splits `input` into its first whitespace-separated word and the
+/// (left-trimmed) remainder, e.g. `"set Vehicle.Speed 10"` ->
+/// `("set", "Vehicle.Speed 10")`.
+pub fn split_first_word(input: &str) -> (&str, &str) {
+    let input = input.trim();
+    match input.find(char::is_whitespace) {
+        Some(pos) => (&input[..pos], input[pos..].trim_start()),
+        None => (input, ""),
+    }
+}
+
+/// Parses a `[a, b, c]` list literal by splitting it on commas and parsing
+/// each trimmed element (with surrounding `"` removed) as `T`; `[]` is an
+/// empty list.
+pub fn get_array_from_input<T: std::str::FromStr>(input: String) -> Result<Vec<T>, String> {
+    let inner = input
+        .trim()
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(|| format!("expected a `[...]` list, got \"{input}\""))?;
+    if inner.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    inner
+        .split(',')
+        .map(|element| {
+            let element = element.trim().trim_matches('"');
+            element.parse::<T>().map_err(|_| format!("could not parse list element \"{element}\""))
+        })
+        .collect()
+}
+
+/// Parses `input` as a broker URI, in the same form `--server` accepts.
+pub fn to_uri(input: &str) -> Result<tonic::transport::Uri, String> {
+    kuksa_common::to_uri(input).map_err(|err| err.to_string())
+}
+
+pub fn print_info(msg: impl std::fmt::Display) -> std::io::Result<()> {
+    println!("{msg}");
+    Ok(())
+}
+
+pub fn print_error(cmd: &str, msg: impl std::fmt::Display) -> std::io::Result<()> {
+    println!("{} {cmd}: {msg}", Color::Red.paint("Error"));
+    Ok(())
+}
+
+pub fn print_resp_ok(cmd: &str) -> std::io::Result<()> {
+    println!("{} {cmd}", Color::Green.paint("OK"));
+    Ok(())
+}
+
+pub fn print_resp_ok_fmt(cmd: &str, args: std::fmt::Arguments) -> std::io::Result<()> {
+    println!("{} {cmd}: {args}", Color::Green.paint("OK"));
+    Ok(())
+}
+
+pub fn print_resp_err(cmd: &str, status: &tonic::Status) -> std::io::Result<()> {
+    println!("{} {cmd}: {status}", Color::Red.paint("Error"));
+    Ok(())
+}
+
+pub fn print_resp_err_fmt(cmd: &str, args: std::fmt::Arguments) -> std::io::Result<()> {
+    println!("{} {cmd}: {args}", Color::Red.paint("Error"));
+    Ok(())
+}
+
+pub fn print_logo(version: String) {
+    println!("{}", Color::White.bold().paint(format!("sdv-cli {version}")));
+}
+
+/// Sets the prompt to show the connected server/protocol label.
+pub fn set_connected_prompt<Term: Terminal>(interface: &Arc<Interface<Term>>, label: String) {
+    let _ = interface.set_prompt(&format!("{label}> "));
+}
+
+/// Resets the prompt to its disconnected state.
+pub fn set_disconnected_prompt<Term: Terminal>(interface: &Arc<Interface<Term>>) {
+    let _ = interface.set_prompt("not connected> ");
+}
+
+/// Bound to Enter: accepts the current input line.
+pub struct EnterFunction;
+
+impl<Term: Terminal> linefeed::Function<Term> for EnterFunction {
+    fn execute(
+        &self,
+        prompter: &mut linefeed::Prompter<Term>,
+        _count: i32,
+        _ch: char,
+    ) -> std::io::Result<()> {
+        prompter.accept_input()
+    }
+}
@@ -0,0 +1,228 @@
+/********************************************************************************
+* Copyright (c) 2023 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Named connection profiles loaded from a versioned TOML config file.
+//!
+//! A config file looks like:
+//!
+//! ```toml
+//! version = 1
+//!
+//! [profile.local]
+//! uri = "http://127.0.0.1:55555"
+//!
+//! [profile.staging]
+//! uri = "https://staging.example.com:55555"
+//! tls = true
+//! ca_cert = "/etc/kuksa/staging-ca.pem"
+//! token_file = "/etc/kuksa/staging-token.jwt"
+//! ```
+//!
+//! [`watch`] spawns a background task that keeps a [`SharedConfig`] up to date
+//! as the file is edited, so `connect <profile-name>` and completion always see
+//! the latest profiles without restarting the REPL.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use notify::{RecursiveMode, Watcher};
+use serde::Deserialize;
+
+/// The newest config file `version` this CLI knows how to read. Bumped whenever
+/// a breaking change is made to the file format, so older/newer CLIs can give a
+/// clear error instead of silently misreading a profile.
+const CURRENT_CONFIG_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Profile {
+    pub uri: String,
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub token_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default = "default_version")]
+    version: u32,
+    #[serde(default, rename = "profile")]
+    profiles: HashMap<String, Profile>,
+}
+
+fn default_version() -> u32 {
+    CURRENT_CONFIG_VERSION
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub version: u32,
+    pub profiles: HashMap<String, Profile>,
+}
+
+impl Config {
+    fn from_file(path: &Path) -> Result<Config, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read \"{}\": {err}", path.display()))?;
+        let parsed: ConfigFile = toml::from_str(&content)
+            .map_err(|err| format!("Failed to parse \"{}\": {err}", path.display()))?;
+
+        if parsed.version > CURRENT_CONFIG_VERSION {
+            return Err(format!(
+                "config file version {} is newer than the version {CURRENT_CONFIG_VERSION} this CLI understands",
+                parsed.version
+            ));
+        }
+
+        Ok(Config {
+            version: parsed.version,
+            profiles: parsed.profiles,
+        })
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.get(name)
+    }
+
+    pub fn profile_names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+}
+
+/// A [`Config`] that can be cheaply cloned and is kept current in the background
+/// by [`watch`].
+#[derive(Clone)]
+pub struct SharedConfig(Arc<RwLock<Config>>);
+
+impl Default for SharedConfig {
+    fn default() -> Self {
+        SharedConfig(Arc::new(RwLock::new(Config::default())))
+    }
+}
+
+impl SharedConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Ok(SharedConfig(Arc::new(RwLock::new(Config::from_file(path)?))))
+    }
+
+    pub fn get(&self) -> Config {
+        self.0.read().unwrap().clone()
+    }
+
+    fn replace(&self, config: Config) {
+        *self.0.write().unwrap() = config;
+    }
+}
+
+/// Spawns a filesystem watcher that reloads `path` into `shared` whenever it
+/// changes. The returned [`Watcher`] must be kept alive for as long as reloads
+/// should keep happening; dropping it stops the watch. Parse errors while
+/// editing are reported to stderr and otherwise ignored, so a transient typo
+/// mid-save doesn't tear down the session.
+pub fn watch(path: PathBuf, shared: SharedConfig) -> notify::Result<impl Watcher> {
+    let watched_path = path.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if !(event.kind.is_modify() || event.kind.is_create()) {
+            return;
+        }
+        match Config::from_file(&watched_path) {
+            Ok(config) => shared.replace(config),
+            Err(err) => eprintln!("Failed to reload config: {err}"),
+        }
+    })?;
+    watcher.watch(&path, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// A scratch file under the OS temp dir, removed again when dropped.
+    struct ScratchFile(PathBuf);
+
+    impl Drop for ScratchFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Writes `contents` to a scratch file under the OS temp dir, unique per
+    /// call so concurrent tests don't collide.
+    fn write_config(contents: &str) -> (ScratchFile, PathBuf) {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!(
+            "sdv-cli-config-test-{}-{n}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        (ScratchFile(path.clone()), path)
+    }
+
+    #[test]
+    fn loads_profiles_from_file() {
+        let (_dir, path) = write_config(
+            r#"
+            version = 1
+
+            [profile.local]
+            uri = "http://127.0.0.1:55555"
+            "#,
+        );
+
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.version, 1);
+        assert_eq!(config.profile("local").unwrap().uri, "http://127.0.0.1:55555");
+        assert_eq!(config.profile_names().collect::<Vec<_>>(), vec!["local"]);
+    }
+
+    #[test]
+    fn profile_lookup_miss_returns_none() {
+        let (_dir, path) = write_config("version = 1\n");
+        let config = Config::from_file(&path).unwrap();
+        assert!(config.profile("does-not-exist").is_none());
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let (_dir, path) = write_config("version = 999\n");
+        let err = Config::from_file(&path).unwrap_err();
+        assert!(err.contains("999"), "error should mention the offending version: {err}");
+        assert!(
+            err.contains(&CURRENT_CONFIG_VERSION.to_string()),
+            "error should mention the version this CLI understands: {err}"
+        );
+    }
+
+    #[test]
+    fn missing_version_defaults_to_current() {
+        let (_dir, path) = write_config(
+            r#"
+            [profile.local]
+            uri = "http://127.0.0.1:55555"
+            "#,
+        );
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.version, CURRENT_CONFIG_VERSION);
+    }
+}
@@ -0,0 +1,143 @@
+/********************************************************************************
+* Copyright (c) 2023 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! A VSS-path glob matcher, shared by `metadata PATTERN` filtering and scope
+//! (`verb:glob`) checks — anywhere a signal path needs matching against a
+//! user-supplied pattern rather than an exact name.
+//!
+//! [`PathMatcher::new`] compiles a glob into an anchored [`regex::Regex`]:
+//! - `.` is a literal VSS path segment separator.
+//! - `**` matches any run of characters, `.` included, so it spans
+//!   segments, e.g. `Vehicle.**` matches `Vehicle.Cabin.Door.Row1.Left.IsOpen`.
+//!   The `.` written next to it is still required: `Vehicle.**.IsOpen`
+//!   does not match `Vehicle.IsOpen`.
+//! - `*` matches within a single segment, e.g. `Vehicle.*.IsOpen` matches
+//!   `Vehicle.Cabin.IsOpen` but not `Vehicle.Cabin.Door.IsOpen`.
+//! - `?` matches a single character within a segment.
+//! - `[abc]`/`[a-z]` character classes are passed through to the regex
+//!   engine unchanged, e.g. `Vehicle.Cabin.Door[12]`.
+//!
+//! [`PathMatcher::features`] records which of these were actually used, so a
+//! caller like entry-path completion can decide how much of the glob syntax
+//! it needs to emulate without recompiling the pattern itself.
+
+/// Which glob features a [`PathMatcher`] actually used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlobFeatures {
+    pub recursive: bool,
+    pub wildcard: bool,
+    pub any_char: bool,
+    pub char_class: bool,
+}
+
+/// An anchored VSS-path matcher compiled from a glob pattern (see module docs
+/// for the supported syntax).
+#[derive(Debug, Clone)]
+pub struct PathMatcher {
+    regex: regex::Regex,
+    features: GlobFeatures,
+}
+
+impl PathMatcher {
+    /// Compiles `pattern` into a matcher anchored to the whole path.
+    pub fn new(pattern: impl AsRef<str>) -> Result<PathMatcher, regex::Error> {
+        let mut regex_src = String::from("^");
+        let mut features = GlobFeatures::default();
+
+        let mut chars = pattern.as_ref().chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    features.recursive = true;
+                    regex_src.push_str(".*");
+                }
+                '*' => {
+                    features.wildcard = true;
+                    regex_src.push_str("[^.]*");
+                }
+                '?' => {
+                    features.any_char = true;
+                    regex_src.push_str("[^.]");
+                }
+                '[' => {
+                    features.char_class = true;
+                    regex_src.push('[');
+                    for class_ch in chars.by_ref() {
+                        regex_src.push(class_ch);
+                        if class_ch == ']' {
+                            break;
+                        }
+                    }
+                }
+                '.' => regex_src.push_str(r"\."),
+                other => regex_src.push_str(&regex::escape(&other.to_string())),
+            }
+        }
+        regex_src.push('$');
+
+        Ok(PathMatcher {
+            regex: regex::Regex::new(&regex_src)?,
+            features,
+        })
+    }
+
+    /// Whether `path` matches this pattern.
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(path)
+    }
+
+    /// Which glob features this pattern actually used.
+    pub fn features(&self) -> GlobFeatures {
+        self.features
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recursive_wildcard_spans_segments() {
+        let matcher = PathMatcher::new("Vehicle.**").unwrap();
+        assert!(matcher.is_match("Vehicle.Cabin.Door.Row1.Left.IsOpen"));
+        assert!(matcher.is_match("Vehicle.Speed"));
+        assert!(!matcher.is_match("Other.Speed"));
+        assert!(matcher.features().recursive);
+    }
+
+    #[test]
+    fn single_segment_wildcard_does_not_span_dots() {
+        let matcher = PathMatcher::new("Vehicle.*.IsOpen").unwrap();
+        assert!(matcher.is_match("Vehicle.Cabin.IsOpen"));
+        assert!(!matcher.is_match("Vehicle.Cabin.Door.IsOpen"));
+        assert!(matcher.features().wildcard);
+    }
+
+    #[test]
+    fn character_class_restricts_match() {
+        let matcher = PathMatcher::new("Vehicle.Cabin.Door[12]").unwrap();
+        assert!(matcher.is_match("Vehicle.Cabin.Door1"));
+        assert!(matcher.is_match("Vehicle.Cabin.Door2"));
+        assert!(!matcher.is_match("Vehicle.Cabin.Door3"));
+        assert!(matcher.features().char_class);
+    }
+
+    #[test]
+    fn plain_path_matches_only_itself() {
+        let matcher = PathMatcher::new("Vehicle.Speed").unwrap();
+        assert!(matcher.is_match("Vehicle.Speed"));
+        assert!(!matcher.is_match("Vehicle.Speeds"));
+        assert_eq!(matcher.features(), GlobFeatures::default());
+    }
+}
@@ -0,0 +1,349 @@
+/********************************************************************************
+* Copyright (c) 2023 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! A small client-side filter/transform pipeline for `subscribe`.
+//!
+//! Stages are appended to a subscribe query with `|`, e.g.:
+//!
+//! ```text
+//! subscribe SELECT Vehicle.Speed | where value > 100 | select value
+//! ```
+//!
+//! Supported stages, evaluated left-to-right against every subscription
+//! message:
+//! - `where value <op> <literal>` — keeps each field only if its own value
+//!   compares true against `<literal>`, checked independently per field.
+//!   `<op>` is one of `==`, `!=`, `<`, `<=`, `>`, `>=`.
+//! - `where <path> <op> <literal> [any|all]` — gates the whole message on
+//!   one named (subscribed) path's value, via [`crate::predicate::Predicate`]
+//!   (see that module for array element-wise/`any`/`all` semantics).
+//! - `select a,b` — keeps only the named fields.
+//! - `unique` — drops fields whose value hasn't changed since the last
+//!   message that included them.
+
+use std::collections::HashMap;
+
+use databroker_proto::sdv::databroker::v1 as proto;
+
+use crate::predicate::Predicate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl std::str::FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "==" => Ok(Comparison::Eq),
+            "!=" => Ok(Comparison::Ne),
+            "<" => Ok(Comparison::Lt),
+            "<=" => Ok(Comparison::Le),
+            ">" => Ok(Comparison::Gt),
+            ">=" => Ok(Comparison::Ge),
+            other => Err(format!(
+                "unknown comparison operator \"{other}\" (expected one of: ==, !=, <, <=, >, >=)"
+            )),
+        }
+    }
+}
+
+fn eval_comparison<T: PartialOrd>(value: T, op: Comparison, literal: T) -> bool {
+    match op {
+        Comparison::Eq => value == literal,
+        Comparison::Ne => value != literal,
+        Comparison::Lt => value < literal,
+        Comparison::Le => value <= literal,
+        Comparison::Gt => value > literal,
+        Comparison::Ge => value >= literal,
+    }
+}
+
+/// Compares a datapoint's value against a literal, loosely typed to whatever
+/// variant the value happens to be. Arrays and the failure sentinel never
+/// match, since there's no sensible scalar comparison for them.
+fn compare_value(value: &proto::datapoint::Value, op: Comparison, literal: &str) -> bool {
+    use proto::datapoint::Value::*;
+    match value {
+        BoolValue(value) => literal
+            .parse::<bool>()
+            .is_ok_and(|literal| eval_comparison(*value, op, literal)),
+        Int32Value(value) => literal
+            .parse::<i64>()
+            .is_ok_and(|literal| eval_comparison(i64::from(*value), op, literal)),
+        Int64Value(value) => literal
+            .parse::<i64>()
+            .is_ok_and(|literal| eval_comparison(*value, op, literal)),
+        Uint32Value(value) => literal
+            .parse::<u64>()
+            .is_ok_and(|literal| eval_comparison(u64::from(*value), op, literal)),
+        Uint64Value(value) => literal
+            .parse::<u64>()
+            .is_ok_and(|literal| eval_comparison(*value, op, literal)),
+        FloatValue(value) => literal
+            .parse::<f64>()
+            .is_ok_and(|literal| eval_comparison(f64::from(*value), op, literal)),
+        DoubleValue(value) => literal
+            .parse::<f64>()
+            .is_ok_and(|literal| eval_comparison(*value, op, literal)),
+        StringValue(value) => eval_comparison(value.as_str(), op, literal),
+        FailureValue(_)
+        | StringArray(_)
+        | BoolArray(_)
+        | Int32Array(_)
+        | Int64Array(_)
+        | Uint32Array(_)
+        | Uint64Array(_)
+        | FloatArray(_)
+        | DoubleArray(_) => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Stage {
+    /// `where value <op> <literal>` — no metadata needed, checked
+    /// independently per field.
+    WhereValue {
+        op: Comparison,
+        literal: String,
+    },
+    /// `where <path> <op> <literal> [any|all]` — one named path gates the
+    /// whole message.
+    WherePath(std::sync::Arc<Predicate>),
+    Select {
+        fields: Vec<String>,
+    },
+    Unique,
+}
+
+impl Stage {
+    fn parse(input: &str, metadata: &[proto::Metadata]) -> Result<Stage, String> {
+        let input = input.trim();
+        let (keyword, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let rest = rest.trim();
+
+        match keyword {
+            "where" => {
+                let (field, _) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if field == "value" {
+                    let words: Vec<&str> = rest.split_whitespace().collect();
+                    let ["value", op, literal] = words[..] else {
+                        return Err(format!(
+                            "`where value` expects `value <op> <literal>`, got \"{input}\""
+                        ));
+                    };
+                    Ok(Stage::WhereValue {
+                        op: op.parse()?,
+                        literal: literal.to_owned(),
+                    })
+                } else {
+                    Ok(Stage::WherePath(std::sync::Arc::new(Predicate::parse(
+                        rest, metadata,
+                    )?)))
+                }
+            }
+            "select" => {
+                let fields: Vec<String> = rest
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|field| !field.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+                if fields.is_empty() {
+                    return Err(format!("`select` expects at least one field, got \"{input}\""));
+                }
+                Ok(Stage::Select { fields })
+            }
+            "unique" => Ok(Stage::Unique),
+            other => Err(format!(
+                "unknown pipeline stage \"{other}\" (expected one of: where, select, unique)"
+            )),
+        }
+    }
+}
+
+/// A parsed chain of [`Stage`]s plus the state (last-seen values, for
+/// `unique`) needed to evaluate them across successive subscription messages.
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+    last_values: HashMap<String, proto::datapoint::Value>,
+}
+
+impl Pipeline {
+    /// Splits a `subscribe` argument string into the underlying query and its
+    /// pipeline stages, e.g. `"SELECT Vehicle.Speed | where value > 100"` ->
+    /// `("SELECT Vehicle.Speed", Pipeline{ ... })`. `metadata` is used to
+    /// resolve `DataType`s for any `where <path> ...` stage's literal.
+    pub fn parse(input: &str, metadata: &[proto::Metadata]) -> Result<(String, Pipeline), String> {
+        let mut parts = input.split('|');
+        let query = parts.next().unwrap_or("").trim().to_owned();
+        let stages = parts
+            .map(|stage| Stage::parse(stage, metadata))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((
+            query,
+            Pipeline {
+                stages,
+                last_values: HashMap::new(),
+            },
+        ))
+    }
+
+    /// Applies the pipeline to one subscription message's fields, in order,
+    /// returning the fields that should still be rendered.
+    pub fn apply(
+        &mut self,
+        mut rows: Vec<(String, proto::Datapoint)>,
+    ) -> Vec<(String, proto::Datapoint)> {
+        for stage in &self.stages {
+            if rows.is_empty() {
+                break;
+            }
+            match stage {
+                Stage::WhereValue { op, literal } => {
+                    rows.retain(|(_, datapoint)| {
+                        datapoint
+                            .value
+                            .as_ref()
+                            .is_some_and(|value| compare_value(value, *op, literal))
+                    });
+                }
+                Stage::WherePath(predicate) => {
+                    let passes = rows
+                        .iter()
+                        .find(|(name, _)| *name == predicate.path)
+                        .and_then(|(_, datapoint)| datapoint.value.as_ref())
+                        .is_some_and(|value| predicate.eval(value));
+                    if !passes {
+                        rows.clear();
+                    }
+                }
+                Stage::Select { fields } => {
+                    rows.retain(|(name, _)| fields.iter().any(|field| field == name));
+                }
+                Stage::Unique => {
+                    rows.retain(|(name, datapoint)| {
+                        let changed = self.last_values.get(name) != datapoint.value.as_ref();
+                        if changed {
+                            if let Some(value) = &datapoint.value {
+                                self.last_values.insert(name.clone(), value.clone());
+                            }
+                        }
+                        changed
+                    });
+                }
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn datapoint(value: proto::datapoint::Value) -> proto::Datapoint {
+        proto::Datapoint {
+            timestamp: None,
+            value: Some(value),
+        }
+    }
+
+    #[test]
+    fn parse_splits_query_from_stages() {
+        let (query, pipeline) =
+            Pipeline::parse("SELECT Vehicle.Speed | where value > 100 | select value", &[])
+                .unwrap();
+        assert_eq!(query, "SELECT Vehicle.Speed");
+        assert_eq!(pipeline.stages.len(), 2);
+    }
+
+    #[test]
+    fn parse_with_no_stages_keeps_whole_query() {
+        let (query, pipeline) = Pipeline::parse("SELECT Vehicle.Speed", &[]).unwrap();
+        assert_eq!(query, "SELECT Vehicle.Speed");
+        assert!(pipeline.stages.is_empty());
+    }
+
+    #[test]
+    fn where_value_requires_exactly_op_and_literal() {
+        let err = Stage::parse("where value > ", &[]).unwrap_err();
+        assert!(err.contains("where value"), "{err}");
+    }
+
+    #[test]
+    fn select_requires_at_least_one_field() {
+        let err = Stage::parse("select ", &[]).unwrap_err();
+        assert!(err.contains("select"), "{err}");
+    }
+
+    #[test]
+    fn unknown_stage_is_rejected() {
+        let err = Stage::parse("groupby value", &[]).unwrap_err();
+        assert!(err.contains("groupby"), "{err}");
+    }
+
+    #[test]
+    fn where_value_filters_rows_independently() {
+        let (_, mut pipeline) = Pipeline::parse("X | where value > 100", &[]).unwrap();
+        let rows = vec![
+            ("Vehicle.Speed".to_owned(), datapoint(proto::datapoint::Value::Int32Value(150))),
+            ("Vehicle.RPM".to_owned(), datapoint(proto::datapoint::Value::Int32Value(50))),
+        ];
+        let kept = pipeline.apply(rows);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].0, "Vehicle.Speed");
+    }
+
+    #[test]
+    fn select_keeps_only_named_fields() {
+        let (_, mut pipeline) = Pipeline::parse("X | select Vehicle.Speed", &[]).unwrap();
+        let rows = vec![
+            ("Vehicle.Speed".to_owned(), datapoint(proto::datapoint::Value::Int32Value(1))),
+            ("Vehicle.RPM".to_owned(), datapoint(proto::datapoint::Value::Int32Value(2))),
+        ];
+        let kept = pipeline.apply(rows);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].0, "Vehicle.Speed");
+    }
+
+    #[test]
+    fn unique_drops_repeated_values_across_calls() {
+        let (_, mut pipeline) = Pipeline::parse("X | unique", &[]).unwrap();
+        let row = vec![(
+            "Vehicle.Speed".to_owned(),
+            datapoint(proto::datapoint::Value::Int32Value(42)),
+        )];
+
+        let first = pipeline.apply(row.clone());
+        assert_eq!(first.len(), 1, "first sighting of a value should pass through");
+
+        let second = pipeline.apply(row.clone());
+        assert!(second.is_empty(), "repeated, unchanged value should be dropped");
+
+        let changed = vec![(
+            "Vehicle.Speed".to_owned(),
+            datapoint(proto::datapoint::Value::Int32Value(43)),
+        )];
+        let third = pipeline.apply(changed);
+        assert_eq!(third.len(), 1, "a changed value should pass through again");
+    }
+}
@@ -0,0 +1,348 @@
+/********************************************************************************
+* Copyright (c) 2023 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! A reusable predicate evaluator for gating signal updates, e.g.
+//! `Vehicle.Speed > 50` or, for an array-valued signal,
+//! `Vehicle.ADAS.Flags == [true, false] any`.
+//!
+//! A [`Predicate`] parses as `<path> <op> <literal> [any|all]`, with `<op>`
+//! one of `==, !=, <, <=, >, >=`. The literal is coerced against the path's
+//! VSS `DataType` (looked up from metadata) with
+//! [`DataValue::try_from`](crate::value::DataValue), the same conversion
+//! `set`/`feed` use, so a predicate's literal is held to the same
+//! range/format rules as a value you'd actually send.
+//!
+//! Scalar comparisons reduce trivially to a single bool. Array-valued
+//! signals are compared element-wise against the (array) literal, producing
+//! a mask of `Option<bool>` — `None` where either side's element is missing
+//! or either whole datapoint is `FailureValue`/`NotAvailable` — which is then
+//! reduced with the requested `any`/`all` selector (`all` by default). A
+//! `None` in the mask never counts towards `any` or `all` being true, and a
+//! reduction with no non-null elements at all is non-matching, mirroring how
+//! Arrow's comparison kernels combine validity bitmaps: a null never makes a
+//! predicate newly true, it just drops out of consideration.
+
+use databroker_proto::sdv::databroker::v1 as proto;
+
+use crate::value::DataValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl std::str::FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "==" => Ok(Comparison::Eq),
+            "!=" => Ok(Comparison::Ne),
+            "<" => Ok(Comparison::Lt),
+            "<=" => Ok(Comparison::Le),
+            ">" => Ok(Comparison::Gt),
+            ">=" => Ok(Comparison::Ge),
+            other => Err(format!(
+                "unknown comparison operator \"{other}\" (expected one of: ==, !=, <, <=, >, >=)"
+            )),
+        }
+    }
+}
+
+fn eval_comparison<T: PartialOrd>(lhs: T, op: Comparison, rhs: T) -> bool {
+    match op {
+        Comparison::Eq => lhs == rhs,
+        Comparison::Ne => lhs != rhs,
+        Comparison::Lt => lhs < rhs,
+        Comparison::Le => lhs <= rhs,
+        Comparison::Gt => lhs > rhs,
+        Comparison::Ge => lhs >= rhs,
+    }
+}
+
+/// How an array-valued predicate's per-element comparisons combine into one
+/// bool. Mirrors SQL's three-valued `ANY`/`ALL`: nulls never make the result
+/// newly true, and an all-null mask is non-matching either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reducer {
+    Any,
+    All,
+}
+
+impl std::str::FromStr for Reducer {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "any" => Ok(Reducer::Any),
+            "all" => Ok(Reducer::All),
+            other => Err(format!(
+                "unknown reducer \"{other}\" (expected one of: any, all)"
+            )),
+        }
+    }
+}
+
+/// A single `<path> <op> <literal> [any|all]` predicate, with the literal
+/// already coerced to the path's `DataType`.
+#[derive(Debug)]
+pub struct Predicate {
+    pub path: String,
+    op: Comparison,
+    literal: proto::datapoint::Value,
+    reducer: Reducer,
+}
+
+impl Predicate {
+    /// Parses `input` and coerces its literal against `path`'s `DataType` as
+    /// found in `metadata`.
+    pub fn parse(input: &str, metadata: &[proto::Metadata]) -> Result<Predicate, String> {
+        // The literal may itself contain spaces (e.g. `[true, false]`), so
+        // only the path, the op and an optional trailing reducer are split off.
+        let input = input.trim();
+        let (path, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let rest = rest.trim_start();
+        let (op, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let rest = rest.trim();
+        let (literal, reducer) = match rest.rsplit_once(char::is_whitespace) {
+            Some((literal, reducer @ ("any" | "all"))) => (literal.trim_end(), reducer.parse()?),
+            _ => (rest, Reducer::All),
+        };
+        if path.is_empty() || op.is_empty() || literal.is_empty() {
+            return Err(format!(
+                "expected `<path> <op> <literal> [any|all]`, got \"{input}\""
+            ));
+        }
+
+        let entry = metadata
+            .iter()
+            .find(|entry| entry.name == path)
+            .ok_or_else(|| format!("no metadata available for \"{path}\""))?;
+        let data_type = proto::DataType::from_i32(entry.data_type)
+            .ok_or_else(|| format!("\"{path}\" has an unknown data type"))?;
+
+        let literal = DataValue::try_from((literal, data_type))
+            .map_err(|err| err.to_string())?
+            .0;
+
+        Ok(Predicate {
+            path: path.to_owned(),
+            op: op.parse()?,
+            literal,
+            reducer,
+        })
+    }
+
+    /// Evaluates the predicate against one path's current value. Returns
+    /// `false` (non-matching) rather than propagating a null when the value
+    /// is unavailable, or the array reduction sees no non-null elements.
+    pub fn eval(&self, value: &proto::datapoint::Value) -> bool {
+        eval_masked(value, self.op, &self.literal)
+            .map(|mask| mask.reduce(self.reducer))
+            .unwrap_or(false)
+    }
+}
+
+/// The result of comparing one datapoint value against a literal: a single
+/// nullable bool for scalars, or a per-element nullable mask for arrays.
+enum Mask {
+    Scalar(Option<bool>),
+    Array(Vec<Option<bool>>),
+}
+
+impl Mask {
+    fn reduce(self, reducer: Reducer) -> bool {
+        match self {
+            Mask::Scalar(result) => result.unwrap_or(false),
+            Mask::Array(mask) => {
+                let mut any_true = false;
+                let mut all_non_null_true = true;
+                let mut saw_non_null = false;
+                for element in mask {
+                    match element {
+                        Some(true) => {
+                            any_true = true;
+                            saw_non_null = true;
+                        }
+                        Some(false) => {
+                            all_non_null_true = false;
+                            saw_non_null = true;
+                        }
+                        None => {}
+                    }
+                }
+                match reducer {
+                    Reducer::Any => any_true,
+                    Reducer::All => saw_non_null && all_non_null_true,
+                }
+            }
+        }
+    }
+}
+
+fn zip_eval<T: PartialOrd + Copy>(lhs: &[T], op: Comparison, rhs: &[T]) -> Vec<Option<bool>> {
+    let len = lhs.len().max(rhs.len());
+    (0..len)
+        .map(|i| match (lhs.get(i), rhs.get(i)) {
+            (Some(lhs), Some(rhs)) => Some(eval_comparison(*lhs, op, *rhs)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// An unavailable datapoint (`FailureValue`) compares as null on either side.
+fn eval_masked(lhs: &proto::datapoint::Value, op: Comparison, rhs: &proto::datapoint::Value) -> Option<Mask> {
+    use proto::datapoint::Value::*;
+    match (lhs, rhs) {
+        (FailureValue(_), _) | (_, FailureValue(_)) => None,
+        (BoolValue(lhs), BoolValue(rhs)) => Some(Mask::Scalar(Some(eval_comparison(*lhs, op, *rhs)))),
+        (Int32Value(lhs), Int32Value(rhs)) => Some(Mask::Scalar(Some(eval_comparison(*lhs, op, *rhs)))),
+        (Int64Value(lhs), Int64Value(rhs)) => Some(Mask::Scalar(Some(eval_comparison(*lhs, op, *rhs)))),
+        (Uint32Value(lhs), Uint32Value(rhs)) => Some(Mask::Scalar(Some(eval_comparison(*lhs, op, *rhs)))),
+        (Uint64Value(lhs), Uint64Value(rhs)) => Some(Mask::Scalar(Some(eval_comparison(*lhs, op, *rhs)))),
+        (FloatValue(lhs), FloatValue(rhs)) => Some(Mask::Scalar(Some(eval_comparison(*lhs, op, *rhs)))),
+        (DoubleValue(lhs), DoubleValue(rhs)) => Some(Mask::Scalar(Some(eval_comparison(*lhs, op, *rhs)))),
+        (StringValue(lhs), StringValue(rhs)) => {
+            Some(Mask::Scalar(Some(eval_comparison(lhs.as_str(), op, rhs.as_str()))))
+        }
+        (BoolArray(lhs), BoolArray(rhs)) => Some(Mask::Array(zip_eval(&lhs.values, op, &rhs.values))),
+        (Int32Array(lhs), Int32Array(rhs)) => Some(Mask::Array(zip_eval(&lhs.values, op, &rhs.values))),
+        (Int64Array(lhs), Int64Array(rhs)) => Some(Mask::Array(zip_eval(&lhs.values, op, &rhs.values))),
+        (Uint32Array(lhs), Uint32Array(rhs)) => Some(Mask::Array(zip_eval(&lhs.values, op, &rhs.values))),
+        (Uint64Array(lhs), Uint64Array(rhs)) => Some(Mask::Array(zip_eval(&lhs.values, op, &rhs.values))),
+        (FloatArray(lhs), FloatArray(rhs)) => Some(Mask::Array(zip_eval(&lhs.values, op, &rhs.values))),
+        (DoubleArray(lhs), DoubleArray(rhs)) => Some(Mask::Array(zip_eval(&lhs.values, op, &rhs.values))),
+        (StringArray(lhs), StringArray(rhs)) => {
+            let mask = (0..lhs.values.len().max(rhs.values.len()))
+                .map(|i| match (lhs.values.get(i), rhs.values.get(i)) {
+                    (Some(lhs), Some(rhs)) => Some(eval_comparison(lhs.as_str(), op, rhs.as_str())),
+                    _ => None,
+                })
+                .collect();
+            Some(Mask::Array(mask))
+        }
+        // Mismatched variants (e.g. comparing against a literal parsed for a
+        // different `DataType`) have no sensible comparison.
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn metadata(path: &str, data_type: proto::DataType) -> proto::Metadata {
+        proto::Metadata {
+            id: 1,
+            name: path.to_owned(),
+            data_type: data_type.into(),
+            entry_type: proto::EntryType::Sensor.into(),
+            change_type: proto::ChangeType::OnChange.into(),
+            description: "".into(),
+        }
+    }
+
+    #[test]
+    fn parse_defaults_to_all_without_a_reducer() {
+        let metadata = [metadata("Vehicle.Speed", proto::DataType::Int32)];
+        let predicate = Predicate::parse("Vehicle.Speed > 100", &metadata).unwrap();
+        assert_eq!(predicate.path, "Vehicle.Speed");
+        assert_eq!(predicate.reducer, Reducer::All);
+    }
+
+    #[test]
+    fn parse_accepts_an_explicit_reducer() {
+        let metadata = [metadata("Vehicle.Speed", proto::DataType::Int32)];
+        let predicate = Predicate::parse("Vehicle.Speed > 100 any", &metadata).unwrap();
+        assert_eq!(predicate.reducer, Reducer::Any);
+    }
+
+    #[test]
+    fn parse_fails_without_metadata_for_path() {
+        let err = Predicate::parse("Vehicle.Speed > 100", &[]).unwrap_err();
+        assert!(err.contains("Vehicle.Speed"), "{err}");
+    }
+
+    #[test]
+    fn scalar_eval_compares_the_value() {
+        let metadata = [metadata("Vehicle.Speed", proto::DataType::Int32)];
+        let predicate = Predicate::parse("Vehicle.Speed > 100", &metadata).unwrap();
+        assert!(predicate.eval(&proto::datapoint::Value::Int32Value(150)));
+        assert!(!predicate.eval(&proto::datapoint::Value::Int32Value(50)));
+    }
+
+    #[test]
+    fn scalar_eval_is_non_matching_on_failure_value() {
+        let metadata = [metadata("Vehicle.Speed", proto::DataType::Int32)];
+        let predicate = Predicate::parse("Vehicle.Speed > 100", &metadata).unwrap();
+        assert!(!predicate.eval(&proto::datapoint::Value::FailureValue(1)));
+    }
+
+    #[test]
+    fn eval_masked_rejects_mismatched_variants() {
+        let lhs = proto::datapoint::Value::Int32Value(1);
+        let rhs = proto::datapoint::Value::StringValue("1".to_owned());
+        assert!(eval_masked(&lhs, Comparison::Eq, &rhs).is_none());
+    }
+
+    #[test]
+    fn array_any_matches_on_one_true_element() {
+        let lhs = proto::datapoint::Value::Int32Array(proto::Int32Array {
+            values: vec![1, 200, 3],
+        });
+        let rhs = proto::datapoint::Value::Int32Array(proto::Int32Array {
+            values: vec![100, 100, 100],
+        });
+        let mask = eval_masked(&lhs, Comparison::Gt, &rhs).unwrap();
+        assert!(mask.reduce(Reducer::Any));
+    }
+
+    #[test]
+    fn array_all_fails_if_any_element_fails() {
+        let lhs = proto::datapoint::Value::Int32Array(proto::Int32Array {
+            values: vec![200, 1, 300],
+        });
+        let rhs = proto::datapoint::Value::Int32Array(proto::Int32Array {
+            values: vec![100, 100, 100],
+        });
+        let mask = eval_masked(&lhs, Comparison::Gt, &rhs).unwrap();
+        assert!(!mask.reduce(Reducer::All));
+    }
+
+    #[test]
+    fn array_mismatched_lengths_null_out_missing_elements() {
+        let lhs = proto::datapoint::Value::Int32Array(proto::Int32Array { values: vec![200] });
+        let rhs = proto::datapoint::Value::Int32Array(proto::Int32Array {
+            values: vec![100, 100],
+        });
+        let mask = eval_masked(&lhs, Comparison::Gt, &rhs).unwrap();
+        match mask {
+            Mask::Array(elements) => {
+                assert_eq!(elements, vec![Some(true), None]);
+            }
+            Mask::Scalar(_) => panic!("expected an array mask"),
+        }
+    }
+
+    #[test]
+    fn all_null_mask_is_non_matching_for_any_and_all() {
+        let mask = Mask::Array(vec![None, None, None]);
+        assert!(!Mask::Array(vec![None, None, None]).reduce(Reducer::Any));
+        assert!(!mask.reduce(Reducer::All));
+    }
+}
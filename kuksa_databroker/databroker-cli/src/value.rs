@@ -0,0 +1,406 @@
+/********************************************************************************
+* Copyright (c) 2023 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Reversible text <-> [`datapoint::Value`](proto::datapoint::Value)
+//! conversion, keyed by VSS [`DataType`](proto::DataType).
+//!
+//! [`DataValue`] wraps a `datapoint::Value` (needed to implement the foreign
+//! `TryFrom`/`Display`/`From` traits here, since the proto type itself lives
+//! in `databroker_proto`). Parsing goes through
+//! `DataValue::try_from((text, data_type))`; rendering back to the text
+//! `try_from` accepts is `DataValue`'s `Display` impl, so `Value -> text ->
+//! Value` round-trips for every scalar type.
+//!
+//! [`ValueParseError`] carries the offending input, the target `DataType`,
+//! and — for the bounded integer types (`Int8`, `Int16`, `Uint8`, `Uint16`)
+//! — the allowed range, so a failure reads as `"300" out of range for Int8
+//! (-128..=127)` rather than a bare parse failure.
+//!
+//! Integer literals accept `0x`/`0o`/`0b` radix prefixes and `_` digit
+//! separators (e.g. `0xFF`, `0b1010`, `1_000`), both scalar and in arrays —
+//! common in automotive calibration/testing. Float literals accept `_`
+//! separators too; `inf`/`-inf`/`nan`/scientific notation already parse via
+//! `f32`/`f64`'s own `FromStr`.
+
+use std::fmt;
+
+use databroker_proto::sdv::databroker::v1 as proto;
+
+/// A [`proto::datapoint::Value`] with local `TryFrom`/`Display`/`From` impls,
+/// since the proto type is foreign to this crate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataValue(pub proto::datapoint::Value);
+
+/// Why a piece of text couldn't be parsed as a given `DataType`.
+#[derive(Debug, Clone)]
+pub struct ValueParseError {
+    pub input: String,
+    pub data_type: proto::DataType,
+    pub range: Option<String>,
+}
+
+impl ValueParseError {
+    fn new(input: &str, data_type: proto::DataType) -> Self {
+        ValueParseError {
+            input: input.to_owned(),
+            data_type,
+            range: None,
+        }
+    }
+
+    fn with_range(input: &str, data_type: proto::DataType, range: String) -> Self {
+        ValueParseError {
+            input: input.to_owned(),
+            data_type,
+            range: Some(range),
+        }
+    }
+}
+
+impl fmt::Display for ValueParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.range {
+            Some(range) => write!(
+                f,
+                "\"{}\" out of range for {:?} ({range})",
+                self.input, self.data_type
+            ),
+            None => write!(f, "could not parse \"{}\" as {:?}", self.input, self.data_type),
+        }
+    }
+}
+
+impl std::error::Error for ValueParseError {}
+
+macro_rules! from_scalar {
+    ($rust_ty:ty, $variant:ident) => {
+        impl From<$rust_ty> for DataValue {
+            fn from(value: $rust_ty) -> Self {
+                DataValue(proto::datapoint::Value::$variant(value))
+            }
+        }
+    };
+}
+
+from_scalar!(bool, BoolValue);
+from_scalar!(i32, Int32Value);
+from_scalar!(i64, Int64Value);
+from_scalar!(u32, Uint32Value);
+from_scalar!(u64, Uint64Value);
+from_scalar!(f32, FloatValue);
+from_scalar!(f64, DoubleValue);
+from_scalar!(String, StringValue);
+
+macro_rules! from_array {
+    ($rust_ty:ty, $variant:ident, $array_ty:ident) => {
+        impl From<Vec<$rust_ty>> for DataValue {
+            fn from(values: Vec<$rust_ty>) -> Self {
+                DataValue(proto::datapoint::Value::$variant(proto::$array_ty {
+                    values,
+                }))
+            }
+        }
+    };
+}
+
+from_array!(bool, BoolArray, BoolArray);
+from_array!(i32, Int32Array, Int32Array);
+from_array!(i64, Int64Array, Int64Array);
+from_array!(u32, Uint32Array, Uint32Array);
+from_array!(u64, Uint64Array, Uint64Array);
+from_array!(f32, FloatArray, FloatArray);
+from_array!(f64, DoubleArray, DoubleArray);
+from_array!(String, StringArray, StringArray);
+
+/// Parses a single bounded integer type, reporting its allowed range on
+/// failure instead of a bare parse error.
+macro_rules! parse_bounded {
+    ($input:expr, $data_type:expr, $rust_ty:ty, $widen:expr) => {{
+        match parse_int::<$rust_ty>($input) {
+            Ok(value) => Ok(DataValue::from($widen(value))),
+            Err(_) => Err(ValueParseError::with_range(
+                $input,
+                $data_type,
+                format!("{}..={}", <$rust_ty>::MIN, <$rust_ty>::MAX),
+            )),
+        }
+    }};
+}
+
+fn parse_array<T>(input: &str, data_type: proto::DataType) -> Result<Vec<T>, ValueParseError>
+where
+    T: std::str::FromStr,
+{
+    crate::cli::get_array_from_input(input.to_owned())
+        .map_err(|_| ValueParseError::new(input, data_type))
+}
+
+/// Integer types that can be parsed via `from_str_radix`, so [`parse_int`]
+/// can be generic over them.
+trait FromStrRadix: Sized {
+    fn from_str_radix(src: &str, radix: u32) -> Result<Self, std::num::ParseIntError>;
+}
+
+macro_rules! impl_from_str_radix {
+    ($($rust_ty:ty),*) => {
+        $(impl FromStrRadix for $rust_ty {
+            fn from_str_radix(src: &str, radix: u32) -> Result<Self, std::num::ParseIntError> {
+                <$rust_ty>::from_str_radix(src, radix)
+            }
+        })*
+    };
+}
+
+impl_from_str_radix!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+/// Splits an integer literal into the `radix`/digits pair `from_str_radix`
+/// expects: a `0x`/`0o`/`0b` prefix (after any sign) selects the radix, and
+/// `_` digit separators are stripped, e.g. `-0x1_00` -> `(16, "-100")`.
+fn normalize_int_literal(input: &str) -> (u32, String) {
+    let input = input.trim();
+    let (sign, rest) = match input.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", input.strip_prefix('+').unwrap_or(input)),
+    };
+    let (radix, digits) = if let Some(digits) = rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X")) {
+        (16, digits)
+    } else if let Some(digits) = rest.strip_prefix("0o").or_else(|| rest.strip_prefix("0O")) {
+        (8, digits)
+    } else if let Some(digits) = rest.strip_prefix("0b").or_else(|| rest.strip_prefix("0B")) {
+        (2, digits)
+    } else {
+        (10, rest)
+    };
+    (radix, format!("{sign}{}", digits.replace('_', "")))
+}
+
+/// Parses a single integer, accepting `0x`/`0o`/`0b` radix prefixes and `_`
+/// digit separators in addition to plain decimal.
+fn parse_int<T: FromStrRadix>(input: &str) -> Result<T, std::num::ParseIntError> {
+    let (radix, digits) = normalize_int_literal(input);
+    T::from_str_radix(&digits, radix)
+}
+
+/// Parses a single float, accepting `_` digit separators in addition to
+/// plain decimal/scientific notation and `inf`/`-inf`/`nan` (all of which
+/// `f32`/`f64`'s own `FromStr` already handles).
+fn parse_float<T: std::str::FromStr>(input: &str) -> Result<T, T::Err> {
+    input.replace('_', "").parse::<T>()
+}
+
+/// A single array element that parses like [`parse_int`], so
+/// [`parse_int_array`] can reuse `cli::get_array_from_input`'s generic
+/// comma-splitting without duplicating it.
+struct IntLiteral<T>(T);
+
+impl<T: FromStrRadix> std::str::FromStr for IntLiteral<T> {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        parse_int(input).map(IntLiteral)
+    }
+}
+
+fn parse_int_array<T>(input: &str, data_type: proto::DataType) -> Result<Vec<T>, ValueParseError>
+where
+    T: FromStrRadix,
+{
+    crate::cli::get_array_from_input::<IntLiteral<T>>(input.to_owned())
+        .map(|values| values.into_iter().map(|value| value.0).collect())
+        .map_err(|_| ValueParseError::new(input, data_type))
+}
+
+impl TryFrom<(&str, proto::DataType)> for DataValue {
+    type Error = ValueParseError;
+
+    fn try_from((input, data_type): (&str, proto::DataType)) -> Result<Self, Self::Error> {
+        if input == "NotAvailable" {
+            return Ok(DataValue(proto::datapoint::Value::FailureValue(
+                proto::datapoint::Failure::NotAvailable as i32,
+            )));
+        }
+
+        use proto::DataType::*;
+        #[allow(unreachable_patterns)]
+        match data_type {
+            String => Ok(DataValue::from(input.to_owned())),
+            StringArray => parse_array::<String>(input, data_type).map(DataValue::from),
+            Bool => input
+                .parse::<bool>()
+                .map(DataValue::from)
+                .map_err(|_| ValueParseError::new(input, data_type)),
+            BoolArray => parse_array::<bool>(input, data_type).map(DataValue::from),
+            Int8 => parse_bounded!(input, data_type, i8, |value| value as i32),
+            Int8Array => parse_int_array::<i32>(input, data_type).map(DataValue::from),
+            Int16 => parse_bounded!(input, data_type, i16, |value| value as i32),
+            Int16Array => parse_int_array::<i32>(input, data_type).map(DataValue::from),
+            Int32 => parse_int::<i32>(input)
+                .map(DataValue::from)
+                .map_err(|_| ValueParseError::new(input, data_type)),
+            Int32Array => parse_int_array::<i32>(input, data_type).map(DataValue::from),
+            Int64 => parse_int::<i64>(input)
+                .map(DataValue::from)
+                .map_err(|_| ValueParseError::new(input, data_type)),
+            Int64Array => parse_int_array::<i64>(input, data_type).map(DataValue::from),
+            Uint8 => parse_bounded!(input, data_type, u8, |value| value as u32),
+            Uint8Array => parse_int_array::<u32>(input, data_type).map(DataValue::from),
+            Uint16 => parse_bounded!(input, data_type, u16, |value| value as u32),
+            Uint16Array => parse_int_array::<u32>(input, data_type).map(DataValue::from),
+            Uint32 => parse_int::<u32>(input)
+                .map(DataValue::from)
+                .map_err(|_| ValueParseError::new(input, data_type)),
+            Uint32Array => parse_int_array::<u32>(input, data_type).map(DataValue::from),
+            Uint64 => parse_int::<u64>(input)
+                .map(DataValue::from)
+                .map_err(|_| ValueParseError::new(input, data_type)),
+            Uint64Array => parse_int_array::<u64>(input, data_type).map(DataValue::from),
+            Float => parse_float::<f32>(input)
+                .map(DataValue::from)
+                .map_err(|_| ValueParseError::new(input, data_type)),
+            FloatArray => parse_array::<f32>(input, data_type).map(DataValue::from),
+            Double => parse_float::<f64>(input)
+                .map(DataValue::from)
+                .map_err(|_| ValueParseError::new(input, data_type)),
+            DoubleArray => parse_array::<f64>(input, data_type).map(DataValue::from),
+            _ => Err(ValueParseError::new(input, data_type)),
+        }
+    }
+}
+
+impl fmt::Display for DataValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn array_str<T: fmt::Display>(values: &[T]) -> String {
+            format!(
+                "[{}]",
+                values
+                    .iter()
+                    .map(T::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+
+        match &self.0 {
+            proto::datapoint::Value::BoolValue(value) => write!(f, "{value}"),
+            proto::datapoint::Value::Int32Value(value) => write!(f, "{value}"),
+            proto::datapoint::Value::Int64Value(value) => write!(f, "{value}"),
+            proto::datapoint::Value::Uint32Value(value) => write!(f, "{value}"),
+            proto::datapoint::Value::Uint64Value(value) => write!(f, "{value}"),
+            proto::datapoint::Value::FloatValue(value) => write!(f, "{value}"),
+            proto::datapoint::Value::DoubleValue(value) => write!(f, "{value}"),
+            proto::datapoint::Value::StringValue(value) => write!(f, "{value}"),
+            proto::datapoint::Value::BoolArray(array) => write!(f, "{}", array_str(&array.values)),
+            proto::datapoint::Value::Int32Array(array) => write!(f, "{}", array_str(&array.values)),
+            proto::datapoint::Value::Int64Array(array) => write!(f, "{}", array_str(&array.values)),
+            proto::datapoint::Value::Uint32Array(array) => write!(f, "{}", array_str(&array.values)),
+            proto::datapoint::Value::Uint64Array(array) => write!(f, "{}", array_str(&array.values)),
+            proto::datapoint::Value::FloatArray(array) => write!(f, "{}", array_str(&array.values)),
+            proto::datapoint::Value::DoubleArray(array) => write!(f, "{}", array_str(&array.values)),
+            proto::datapoint::Value::StringArray(array) => {
+                write!(f, "{}", array_str(&array.values))
+            }
+            proto::datapoint::Value::FailureValue(failure) => write!(
+                f,
+                "{:?}",
+                proto::datapoint::Failure::from_i32(*failure).unwrap()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(input: &str, data_type: proto::DataType) {
+        let parsed = DataValue::try_from((input, data_type))
+            .unwrap_or_else(|err| panic!("failed to parse \"{input}\" as {data_type:?}: {err}"));
+        let rendered = parsed.to_string();
+        let reparsed = DataValue::try_from((rendered.as_str(), data_type)).unwrap_or_else(|err| {
+            panic!("failed to re-parse rendered \"{rendered}\" as {data_type:?}: {err}")
+        });
+        assert_eq!(parsed, reparsed);
+    }
+
+    #[test]
+    fn round_trips_scalars() {
+        round_trip("true", proto::DataType::Bool);
+        round_trip("-7", proto::DataType::Int32);
+        round_trip("42", proto::DataType::Uint64);
+        round_trip("3.5", proto::DataType::Double);
+        round_trip("hello", proto::DataType::String);
+    }
+
+    #[test]
+    fn bounded_int_out_of_range_reports_range() {
+        let err = DataValue::try_from(("300", proto::DataType::Int8)).unwrap_err();
+        assert_eq!(err.to_string(), "\"300\" out of range for Int8 (-128..=127)");
+    }
+
+    #[test]
+    fn unbounded_int_parse_failure_has_no_range() {
+        let err = DataValue::try_from(("not a number", proto::DataType::Int32)).unwrap_err();
+        assert_eq!(err.to_string(), "could not parse \"not a number\" as Int32");
+    }
+
+    #[test]
+    fn parses_extended_numeric_literals() {
+        assert_eq!(
+            DataValue::try_from(("0xFF", proto::DataType::Int32)).unwrap(),
+            DataValue::from(255)
+        );
+        assert_eq!(
+            DataValue::try_from(("-0x10", proto::DataType::Int32)).unwrap(),
+            DataValue::from(-16)
+        );
+        assert_eq!(
+            DataValue::try_from(("0b1010", proto::DataType::Uint32)).unwrap(),
+            DataValue::from(10u32)
+        );
+        assert_eq!(
+            DataValue::try_from(("0o17", proto::DataType::Int32)).unwrap(),
+            DataValue::from(15)
+        );
+        assert_eq!(
+            DataValue::try_from(("1_000", proto::DataType::Int64)).unwrap(),
+            DataValue::from(1000i64)
+        );
+        assert_eq!(
+            DataValue::try_from(("100", proto::DataType::Int8)).unwrap(),
+            DataValue::try_from(("0x64", proto::DataType::Int8)).unwrap()
+        );
+        assert!(DataValue::try_from(("0x100", proto::DataType::Uint8)).is_err());
+
+        assert_eq!(
+            DataValue::try_from(("[0x1, 0x2, 0x10]", proto::DataType::Int32Array)).unwrap(),
+            DataValue::from(vec![1, 2, 16])
+        );
+
+        assert_eq!(
+            DataValue::try_from(("1e6", proto::DataType::Double)).unwrap(),
+            DataValue::from(1_000_000.0)
+        );
+        assert!(matches!(
+            DataValue::try_from(("inf", proto::DataType::Float)).unwrap().0,
+            proto::datapoint::Value::FloatValue(value) if value.is_infinite()
+        ));
+        assert!(matches!(
+            DataValue::try_from(("nan", proto::DataType::Double)).unwrap().0,
+            proto::datapoint::Value::DoubleValue(value) if value.is_nan()
+        ));
+        assert_eq!(
+            DataValue::try_from(("1_000.5", proto::DataType::Double)).unwrap(),
+            DataValue::from(1000.5)
+        );
+    }
+}